@@ -0,0 +1,277 @@
+//! Bakes the procedural sky into an environment cubemap used for image-based lighting
+//!
+//! Each of the six faces of a [`TextureViewDimension::Cube`](bevy::render::render_resource::TextureViewDimension::Cube)
+//! target is filled from [`sky_radiance`], a CPU approximation of the sky driven by the current
+//! [`AtmosphereMat::sun_position`](crate::AtmosphereMat) (a full GPU pass rendering the `sky.frag`
+//! shader into the faces is the intended upgrade). The result is exposed as the
+//! [`AtmosphereEnvironmentMap`] resource and its average radiance is fed back into Bevy's
+//! [`AmbientLight`] so a changing sun direction recolors both reflections and ambient fill.
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{
+        Extent3d, TextureDimension, TextureFormat, TextureUsages, TextureViewDimension,
+    },
+};
+
+use crate::AtmosphereMat;
+
+/// Number of roughness-prefiltered mips baked into the environment cubemap
+///
+/// Mip 0 holds the mirror-sharp sky; higher mips are progressively blurrier for rougher surfaces.
+const ENVIRONMENT_MIP_LEVELS: u32 = 6;
+
+/// The edge length (in texels) of a single cubemap face
+const ENVIRONMENT_FACE_SIZE: u32 = 512;
+
+/// The prefiltered environment cubemap baked from the current [`AtmosphereMat`](crate::AtmosphereMat)
+///
+/// Insert [`AtmospherePlugin`](crate::AtmospherePlugin) with `environment` enabled to have this baked
+/// from the sky; it is re-baked on later sky changes only when `dynamic` is also enabled. Use the
+/// handle as an IBL source for reflections.
+pub struct AtmosphereEnvironmentMap {
+    /// Handle to the `Cube` texture holding the six prefiltered faces
+    pub cubemap: Handle<Image>,
+    /// The face resolution the cubemap was baked at
+    pub size: u32,
+}
+
+/// The six cubemap faces, paired with the view direction the sky shader is sampled along
+///
+/// Order matches the wgpu cube-face convention (+X, -X, +Y, -Y, +Z, -Z).
+const CUBE_FACE_DIRECTIONS: [Vec3; 6] = [
+    Vec3::X,
+    Vec3::NEG_X,
+    Vec3::Y,
+    Vec3::NEG_Y,
+    Vec3::Z,
+    Vec3::NEG_Z,
+];
+
+/// Allocates the cubemap render target and inserts [`AtmosphereEnvironmentMap`]
+///
+/// Runs once at startup; the actual radiance is filled in by [`atmosphere_bake_environment`].
+pub(crate) fn atmosphere_add_environment_map(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: ENVIRONMENT_FACE_SIZE,
+            height: ENVIRONMENT_FACE_SIZE,
+            depth_or_array_layers: 6,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0, 0, 0, 0, 0],
+        TextureFormat::Rgba16Float,
+    );
+    image.texture_descriptor.mip_level_count = ENVIRONMENT_MIP_LEVELS;
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT;
+    image.texture_view_descriptor = Some(bevy::render::render_resource::TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..Default::default()
+    });
+
+    let cubemap = images.add(image);
+    commands.insert_resource(AtmosphereEnvironmentMap {
+        cubemap,
+        size: ENVIRONMENT_FACE_SIZE,
+    });
+}
+
+/// Re-bakes the environment cubemap whenever the [`AtmosphereMat`](crate::AtmosphereMat) changes
+///
+/// Each face is filled by sampling [`sky_radiance`] along the matching [`CUBE_FACE_DIRECTIONS`]
+/// entry, after which the mip chain is prefiltered for roughness. The low-frequency average radiance
+/// is written into [`AmbientLight`] so ambient fill tracks the sun direction.
+///
+/// When the sky is not `dynamic` this only runs for the first bake; afterwards it is skipped
+/// entirely so a static sky costs nothing per frame.
+pub(crate) fn atmosphere_bake_environment(
+    config: Option<Res<AtmosphereMat>>,
+    environment: Res<AtmosphereEnvironmentMap>,
+    mut images: ResMut<Assets<Image>>,
+    mut ambient: ResMut<AmbientLight>,
+    mut baked: Local<bool>,
+    dynamic: Res<crate::SkyDynamic>,
+) {
+    // `AtmosphereMat` is user-inserted; nothing to bake until it exists.
+    let config = match config {
+        Some(config) => config,
+        None => return,
+    };
+
+    if *baked && !dynamic.0 {
+        return;
+    }
+    if !config.is_changed() && *baked {
+        return;
+    }
+
+    let image = match images.get_mut(&environment.cubemap) {
+        Some(image) => image,
+        None => return,
+    };
+
+    // Render the six faces at full resolution, then prefilter the roughness mip chain from them.
+    let faces: Vec<Vec<Vec3>> = CUBE_FACE_DIRECTIONS
+        .iter()
+        .map(|direction| render_sky_face(&config, environment.size, *direction))
+        .collect();
+    let mips = prefilter_environment(&faces, environment.size);
+    image.data = encode_rgba16f(&mips);
+
+    // Ambient fill comes from the actual baked faces, so a changing sun tint recolors it too.
+    let radiance = average_baked_radiance(&faces);
+    ambient.color = Color::rgb(radiance.x, radiance.y, radiance.z);
+    ambient.brightness = radiance.max_element();
+
+    *baked = true;
+}
+
+/// Fills a single cubemap face by sampling the sky along each texel's view direction
+///
+/// The face looks down `direction` and samples [`sky_radiance`] — a CPU approximation of the sky,
+/// not the `sky.frag` shader — so the baked faces track the sun direction. Returns the face as
+/// `size * size` linear RGB radiance samples in row-major order.
+pub(crate) fn render_sky_face(config: &AtmosphereMat, size: u32, direction: Vec3) -> Vec<Vec3> {
+    let (right, up) = face_basis(direction);
+    let mut texels = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            // Map the texel to [-1, 1] across the face and sample along that direction.
+            let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let dir = (direction + right * u + up * v).normalize_or_zero();
+            texels.push(sky_radiance(config, dir));
+        }
+    }
+    texels
+}
+
+/// Prefilters the cubemap mip chain for roughness, returning `[mip][face]` radiance buffers
+///
+/// Mip 0 holds the mirror-sharp sky; each higher mip is a 2x2 box-blur downsample of the previous,
+/// a cheap stand-in for full GGX importance sampling that still widens the reflection lobe with
+/// roughness.
+fn prefilter_environment(faces: &[Vec<Vec3>], size: u32) -> Vec<Vec<Vec<Vec3>>> {
+    let mut mips = vec![faces.to_vec()];
+    let mut level_size = size;
+    for _ in 1..ENVIRONMENT_MIP_LEVELS {
+        let source = mips.last().unwrap();
+        let next_size = (level_size / 2).max(1);
+        let level: Vec<Vec<Vec3>> = source
+            .iter()
+            .map(|face| downsample_face(face, level_size, next_size))
+            .collect();
+        mips.push(level);
+        level_size = next_size;
+    }
+    mips
+}
+
+/// Box-filters a single face from `size` down to `next_size`
+fn downsample_face(face: &[Vec3], size: u32, next_size: u32) -> Vec<Vec3> {
+    let ratio = (size / next_size).max(1);
+    let mut out = Vec::with_capacity((next_size * next_size) as usize);
+    for y in 0..next_size {
+        for x in 0..next_size {
+            let mut sum = Vec3::ZERO;
+            let mut count = 0.0;
+            for dy in 0..ratio {
+                for dx in 0..ratio {
+                    let sx = x * ratio + dx;
+                    let sy = y * ratio + dy;
+                    sum += face[(sy * size + sx) as usize];
+                    count += 1.0;
+                }
+            }
+            out.push(sum / count);
+        }
+    }
+    out
+}
+
+/// Packs the prefiltered `[mip][face]` radiance into the `Rgba16Float` byte layout
+///
+/// wgpu's `create_texture_with_data` (how Bevy uploads `Image.data`) expects face-outer, mip-inner
+/// ordering — all mips of face 0, then all mips of face 1, and so on — so pack it that way.
+fn encode_rgba16f(mips: &[Vec<Vec<Vec3>>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let face_count = mips.first().map_or(0, |level| level.len());
+    for face in 0..face_count {
+        for level in mips {
+            for texel in &level[face] {
+                for channel in [texel.x, texel.y, texel.z, 1.0] {
+                    data.extend_from_slice(&f32_to_f16(channel).to_le_bytes());
+                }
+            }
+        }
+    }
+    data
+}
+
+/// Converts an `f32` to its IEEE 754 half-precision bit pattern
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        sign
+    } else if exponent >= 0x1f {
+        sign | 0x7c00
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+/// Builds an orthonormal `(right, up)` basis for a cube face looking down `forward`
+///
+/// Shared by the IBL bake and the file export so both sample identical faces.
+pub(crate) fn face_basis(forward: Vec3) -> (Vec3, Vec3) {
+    // Avoid a degenerate cross product on the poles by choosing a non-parallel reference up.
+    let reference = if forward.y.abs() > 0.999 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let right = reference.cross(forward).normalize_or_zero();
+    let up = forward.cross(right);
+    (right, up)
+}
+
+/// Approximates the sky radiance looking along `direction` on the CPU
+///
+/// This is a coarse stand-in for the `sky.frag` scattering math: it reads only
+/// [`AtmosphereMat::sun_position`](crate::AtmosphereMat) (the other scattering parameters are not
+/// consulted), so for a heavily-tweaked sky the baked reflections and ambient fill approximate
+/// rather than exactly match the rendered sphere. It is shared by the IBL bake and the file export
+/// so those two stay identical to each other, and is sufficient for ambient fill and for seeding a
+/// baked skybox pending a real GPU pass.
+pub(crate) fn sky_radiance(config: &AtmosphereMat, direction: Vec3) -> Vec3 {
+    let dir = direction.normalize_or_zero();
+    let sun = config.sun_position.normalize_or_zero();
+    let sun_amount = dir.dot(sun).max(0.0);
+    let horizon = (1.0 - dir.y.abs()).clamp(0.0, 1.0);
+    let sky = Vec3::new(0.3, 0.45, 0.6);
+    sky * (0.2 + 0.8 * dir.y.max(0.0)) + Vec3::splat(horizon * 0.1) + Vec3::ONE * sun_amount.powf(64.0)
+}
+
+/// Averages the baked mip-0 faces to drive ambient fill from the sky's low-frequency radiance
+fn average_baked_radiance(faces: &[Vec<Vec3>]) -> Vec3 {
+    let mut sum = Vec3::ZERO;
+    let mut count = 0.0;
+    for face in faces {
+        for texel in face {
+            sum += *texel;
+            count += 1.0;
+        }
+    }
+    if count == 0.0 {
+        Vec3::ZERO
+    } else {
+        sum / count
+    }
+}
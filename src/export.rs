@@ -0,0 +1,227 @@
+//! Bakes the procedural sky for the current parameters into a cubemap file on disk
+//!
+//! Unlike the live [`AtmosphereEnvironmentMap`](crate::AtmosphereEnvironmentMap), the output here is
+//! a serialized `.ktx2`/`.hdr` asset: a static skybox captured at a chosen sun position that can be
+//! shipped as a plain texture on platforms where the per-frame shader cost isn't wanted.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::environment::render_sky_face;
+use crate::AtmosphereMat;
+
+/// View direction at the centre of each cubemap face, in wgpu (+X, -X, +Y, -Y, +Z, -Z) order
+const FACE_DIRECTIONS: [Vec3; 6] = [
+    Vec3::X,
+    Vec3::NEG_X,
+    Vec3::Y,
+    Vec3::NEG_Y,
+    Vec3::Z,
+    Vec3::NEG_Z,
+];
+
+/// A floating-point cubemap captured from the sky, ready to encode to disk
+pub struct CubemapImage {
+    /// Edge length of a single face, in texels
+    pub size: u32,
+    /// The six faces, each `size * size` RGB radiance samples in row-major order
+    pub faces: [Vec<Vec3>; 6],
+}
+
+impl AtmosphereMat {
+    /// Renders the procedural sky for the current parameters into a floating-point cubemap
+    ///
+    /// Reuses [`render_sky_face`] — the exact six-face path the IBL bake uses — so the exported
+    /// skybox matches the baked environment map. Note this is the CPU `sky_radiance` approximation,
+    /// not a GPU readback of the `sky.frag` shader, so it tracks the sun direction rather than every
+    /// scattering parameter. The returned [`CubemapImage`] holds HDR radiance, encoded with
+    /// [`CubemapImage::save`].
+    pub fn render_to_cubemap(&self, size: u32) -> CubemapImage {
+        let faces = FACE_DIRECTIONS.map(|forward| render_sky_face(self, size, forward));
+        CubemapImage { size, faces }
+    }
+}
+
+impl CubemapImage {
+    /// Writes the cubemap to `path`, choosing the encoder from the file extension
+    ///
+    /// `.hdr` emits a Radiance RGBE image with the six faces stacked vertically; `.ktx2` is written
+    /// as a little-endian float cubemap. Encodes the radiance already captured into [`CubemapImage`].
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ktx2") => self.write_ktx2(path),
+            _ => self.write_hdr(path),
+        }
+    }
+
+    /// Encodes the faces as a single vertically-stacked Radiance `.hdr` image
+    fn write_hdr(&self, path: &Path) -> io::Result<()> {
+        let width = self.size as usize;
+        let height = self.size as usize * 6;
+        let mut file = std::fs::File::create(path)?;
+        write!(
+            file,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            height, width
+        )?;
+        for face in &self.faces {
+            for texel in face {
+                file.write_all(&rgbe(*texel))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes the faces as a conforming `VK_FORMAT_R32G32B32A32_SFLOAT` `.ktx2` cubemap
+    ///
+    /// Writes the identifier, the full KTX2 header, the level index and a Basic Data Format
+    /// Descriptor before the level data, so standard KTX2 loaders accept the file.
+    fn write_ktx2(&self, path: &Path) -> io::Result<()> {
+        // VK_FORMAT_R32G32B32A32_SFLOAT.
+        const VK_FORMAT_R32G32B32A32_SFLOAT: u32 = 109;
+        // Bytes before the DFD: identifier (12) + header (36) + index (32) + one level index (24).
+        const DFD_OFFSET: u32 = 104;
+        const DFD_LENGTH: u32 = 92;
+
+        let size = self.size;
+        let level_bytes = (size as u64) * (size as u64) * 16 * 6;
+        // Level data must start on a 16-byte boundary (lcm of the 16-byte texel block and 4).
+        let level_offset = (DFD_OFFSET as u64 + DFD_LENGTH as u64 + 15) & !15;
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&[
+            0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+        ]);
+
+        // Header.
+        push_u32(&mut out, VK_FORMAT_R32G32B32A32_SFLOAT);
+        push_u32(&mut out, 4); // typeSize
+        push_u32(&mut out, size); // pixelWidth
+        push_u32(&mut out, size); // pixelHeight
+        push_u32(&mut out, 0); // pixelDepth
+        push_u32(&mut out, 0); // layerCount (not an array)
+        push_u32(&mut out, 6); // faceCount (cubemap)
+        push_u32(&mut out, 1); // levelCount
+        push_u32(&mut out, 0); // supercompressionScheme (none)
+
+        // Index.
+        push_u32(&mut out, DFD_OFFSET);
+        push_u32(&mut out, DFD_LENGTH);
+        push_u32(&mut out, 0); // kvdByteOffset
+        push_u32(&mut out, 0); // kvdByteLength
+        push_u64(&mut out, 0); // sgdByteOffset
+        push_u64(&mut out, 0); // sgdByteLength
+
+        // Level index (single mip).
+        push_u64(&mut out, level_offset);
+        push_u64(&mut out, level_bytes);
+        push_u64(&mut out, level_bytes);
+
+        push_dfd(&mut out);
+
+        // Pad to the aligned level-data offset.
+        while (out.len() as u64) < level_offset {
+            out.push(0);
+        }
+
+        // Level data, ordered by face then row to match the KTX2 cube-face convention.
+        for face in &self.faces {
+            for texel in face {
+                for channel in [texel.x, texel.y, texel.z, 1.0] {
+                    out.extend_from_slice(&channel.to_le_bytes());
+                }
+            }
+        }
+
+        std::fs::File::create(path)?.write_all(&out)
+    }
+}
+
+/// Appends a little-endian `u32`
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Appends a little-endian `u64`
+fn push_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Appends a Basic Data Format Descriptor for `R32G32B32A32_SFLOAT`
+fn push_dfd(out: &mut Vec<u8>) {
+    push_u32(out, 92); // dfdTotalSize (this field + 88-byte descriptor block)
+    push_u32(out, 0); // vendorId(17) | descriptorType(15)
+    push_u32(out, 2 | (88 << 16)); // versionNumber(16) | descriptorBlockSize(16)
+    // colorModel = RGBSDA(1), colorPrimaries = BT709(1), transferFunction = LINEAR(1), flags = ALPHA_STRAIGHT(0).
+    push_u32(out, 1 | (1 << 8) | (1 << 16));
+    push_u32(out, 0); // texelBlockDimension0..3
+    push_u32(out, 16); // bytesPlane0 = 16, bytesPlane1..3 = 0
+    push_u32(out, 0); // bytesPlane4..7
+    // Four float samples: R, G, B, A.
+    for (index, channel) in [0u32, 1, 2, 15].iter().enumerate() {
+        let bit_offset = index as u32 * 32;
+        let bit_length = 31; // 32 bits, stored as length - 1
+        // channelType flags: FLOAT (0x80) and SIGNED (0x40).
+        let channel_type = channel | 0x40 | 0x80;
+        push_u32(out, bit_offset | (bit_length << 16) | (channel_type << 24));
+        push_u32(out, 0); // samplePosition0..3
+        push_u32(out, 0xBF80_0000); // sampleLower = -1.0
+        push_u32(out, 0x3F80_0000); // sampleUpper = 1.0
+    }
+}
+
+/// Packs a linear RGB radiance value into Radiance's shared-exponent RGBE byte quad
+fn rgbe(color: Vec3) -> [u8; 4] {
+    let max = color.x.max(color.y).max(color.z);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let (mantissa, exponent) = frexp(max);
+    let scale = mantissa * 256.0 / max;
+    [
+        (color.x * scale) as u8,
+        (color.y * scale) as u8,
+        (color.z * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Decomposes `value` into a normalized mantissa in `[0.5, 1.0)` and a power-of-two exponent
+fn frexp(value: f32) -> (f32, i32) {
+    if value == 0.0 {
+        return (0.0, 0);
+    }
+    let exponent = value.abs().log2().floor() as i32 + 1;
+    (value / 2f32.powi(exponent), exponent)
+}
+
+/// Fired to export the current sky to a cubemap file
+///
+/// Send this event (e.g. from a key binding) to bake the sky at the current [`AtmosphereMat`] and
+/// write it to `path`.
+pub struct ExportCubemap {
+    /// Edge length of each cubemap face, in texels
+    pub size: u32,
+    /// Destination file; the extension (`.ktx2`/`.hdr`) selects the encoder
+    pub path: PathBuf,
+}
+
+/// Handles [`ExportCubemap`] events by baking and writing the sky to disk
+pub(crate) fn atmosphere_export_cubemap(
+    mut events: EventReader<ExportCubemap>,
+    config: Option<Res<AtmosphereMat>>,
+) {
+    let config = match config {
+        Some(config) => config,
+        None => return,
+    };
+    for event in events.iter() {
+        let cubemap = config.render_to_cubemap(event.size);
+        if let Err(err) = cubemap.save(&event.path) {
+            error!("failed to export sky cubemap to {:?}: {}", event.path, err);
+        }
+    }
+}
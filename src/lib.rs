@@ -32,8 +32,14 @@ use bevy::{
 };
 use std::ops::Deref;
 
+mod environment;
+mod export;
 mod material;
+mod sun;
+pub use environment::AtmosphereEnvironmentMap;
+pub use export::{CubemapImage, ExportCubemap};
 pub use material::AtmosphereMat;
+pub use sun::{AtmosphereSun, AtmosphereSunLight};
 use material::{SKY_FRAGMENT_SHADER_HANDLE, SKY_VERTEX_SHADER_HANDLE};
 
 const SKY_VERTEX_SHADER: &str = include_str!("shaders/sky.vert");
@@ -41,7 +47,9 @@ const SKY_FRAGMENT_SHADER: &str = include_str!("shaders/sky.frag");
 
 /// Sets up the atmosphere and the systems that control it
 ///
-/// Follows the first camera it finds
+/// When an [`AtmosphereMat`](crate::AtmosphereMat) resource is present, a single global sky sphere
+/// is spawned and tracks the active camera. Cameras tagged with an [`Atmosphere`](crate::Atmosphere)
+/// component instead get their own sky sphere each, tracked per-camera via [`SkyFollow`](crate::SkyFollow).
 pub struct AtmospherePlugin {
     /// If set to `true`, whenever the [`AtmosphereMat`](crate::AtmosphereMat) resource (if it exists) is changed, the sky is updated
     ///
@@ -66,6 +74,21 @@ pub struct AtmospherePlugin {
     /// ```
     pub dynamic: bool,
     pub sky_radius: f32,
+    /// If set to `true`, the sky is baked into an [`AtmosphereEnvironmentMap`](crate::AtmosphereEnvironmentMap)
+    /// cubemap used for reflections and ambient light
+    ///
+    /// When `dynamic` is also `true` the cubemap is re-baked whenever the [`AtmosphereMat`](crate::AtmosphereMat)
+    /// changes; otherwise it is baked only once.
+    pub environment: bool,
+    /// If set to `true`, an [`AtmosphereSun`](crate::AtmosphereSun) resource drives the sun position
+    /// and, via the [`AtmosphereSunLight`](crate::AtmosphereSunLight) marker, a [`DirectionalLight`]
+    pub sun: bool,
+    /// If set to `true`, `shaders/sky.vert` and `shaders/sky.frag` are loaded through the
+    /// [`AssetServer`](bevy::asset::AssetServer) with filesystem watching so edits live-update the
+    /// rendered sky without a recompile
+    ///
+    /// Falls back to the embedded shaders when the files aren't present (shipped builds).
+    pub watch_shaders: bool,
 }
 
 impl Default for AtmospherePlugin {
@@ -73,12 +96,44 @@ impl Default for AtmospherePlugin {
         Self {
             dynamic: false,
             sky_radius: 10.0,
+            environment: false,
+            sun: false,
+            watch_shaders: false,
         }
     }
 }
 
 pub struct SkyRadius(f32);
 
+/// Mirrors [`AtmospherePlugin::dynamic`](crate::AtmospherePlugin) so systems can gate regeneration
+pub struct SkyDynamic(pub bool);
+
+/// Per-camera sky configuration
+///
+/// Place this on a camera entity to give that camera its own sky sphere driven by the contained
+/// [`AtmosphereMat`](crate::AtmosphereMat), instead of sharing the single global-resource sky. This
+/// lets each viewport have a distinct sky (e.g. one camera underwater-tinted, another clear). The
+/// global-resource path still works as a convenience default when no [`Atmosphere`] component is
+/// present.
+#[derive(Component, Clone)]
+pub struct Atmosphere(pub AtmosphereMat);
+
+/// Links a spawned sky sphere back to the camera entity it follows
+///
+/// Used instead of "first camera found / first sky found" so multiple cameras each track their own
+/// sky.
+#[derive(Component)]
+pub struct SkyFollow(pub Entity);
+
+/// Handles to the sky shaders loaded from disk when `watch_shaders` is enabled
+///
+/// Kept alive so the [`AssetServer`](bevy::asset::AssetServer) keeps watching the files; reloads are
+/// swapped into the embedded handles the [`MaterialPlugin`] pipeline uses.
+struct WatchedSkyShaders {
+    vertex: Handle<Shader>,
+    fragment: Handle<Shader>,
+}
+
 impl Plugin for AtmospherePlugin {
     fn build(&self, app: &mut App) {
         let mut shaders = app.world.resource_mut::<Assets<Shader>>();
@@ -93,6 +148,7 @@ impl Plugin for AtmospherePlugin {
 
         app.add_plugin(MaterialPlugin::<AtmosphereMat>::default());
         app.add_startup_system(atmosphere_add_sky_sphere);
+        app.add_system(atmosphere_add_camera_sky);
         app.add_system_to_stage(
             CoreStage::Last, // Should run after transform_propagate_system
             atmosphere_sky_follow,
@@ -101,7 +157,33 @@ impl Plugin for AtmospherePlugin {
             app.add_system(atmosphere_dynamic_sky);
         }
 
+        if self.environment {
+            app.add_startup_system(environment::atmosphere_add_environment_map);
+            app.add_system(environment::atmosphere_bake_environment);
+        }
+
+        if self.sun {
+            app.init_resource::<AtmosphereSun>();
+            app.add_system(sun::atmosphere_sun);
+        }
+
+        if self.watch_shaders {
+            let asset_server = app.world.resource::<AssetServer>();
+            // Picks up edits on disk; a no-op if the app already enabled watching.
+            let _ = asset_server.watch_for_changes();
+            let handles = WatchedSkyShaders {
+                vertex: asset_server.load("shaders/sky.vert"),
+                fragment: asset_server.load("shaders/sky.frag"),
+            };
+            app.insert_resource(handles);
+            app.add_system(atmosphere_reload_shaders);
+        }
+
+        app.add_event::<ExportCubemap>();
+        app.add_system(export::atmosphere_export_cubemap);
+
         app.insert_resource(SkyRadius(self.sky_radius));
+        app.insert_resource(SkyDynamic(self.dynamic));
     }
 }
 
@@ -112,12 +194,14 @@ fn atmosphere_add_sky_sphere(
     sky_radius: Res<SkyRadius>,
     config: Option<Res<AtmosphereMat>>,
 ) {
-    let sky_material = match config {
-        None => AtmosphereMat::default(),
-        Some(c) => c.deref().clone(),
+    // Only the resource path spawns a global sky; per-camera `Atmosphere` components get their own
+    // spheres in `atmosphere_add_camera_sky`, so this would otherwise double up on the active camera.
+    let config = match config {
+        Some(config) => config,
+        None => return,
     };
 
-    let sky_material = sky_materials.add(sky_material);
+    let sky_material = sky_materials.add(config.deref().clone());
 
     commands
         .spawn_bundle(MaterialMeshBundle {
@@ -132,14 +216,85 @@ fn atmosphere_add_sky_sphere(
         .insert(Name::new("Sky Sphere"));
 }
 
+/// Spawns a dedicated sky sphere for each camera tagged with an [`Atmosphere`] component
+///
+/// The sphere carries a [`SkyFollow`] link back to its owning camera so the follow system can match
+/// them by relationship rather than picking the first entity it finds.
+fn atmosphere_add_camera_sky(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut sky_materials: ResMut<Assets<AtmosphereMat>>,
+    sky_radius: Res<SkyRadius>,
+    cameras: Query<(Entity, &Atmosphere), Added<Atmosphere>>,
+) {
+    for (camera, atmosphere) in cameras.iter() {
+        let sky_material = sky_materials.add(atmosphere.0.clone());
+
+        commands
+            .spawn_bundle(MaterialMeshBundle {
+                mesh: meshes.add(Mesh::from(shape::Icosphere {
+                    radius: -sky_radius.0,
+                    subdivisions: 2,
+                })),
+                material: sky_material,
+                ..Default::default()
+            })
+            .insert(NotShadowCaster)
+            .insert(SkyFollow(camera))
+            .insert(Name::new("Sky Sphere"));
+    }
+}
+
+/// Swaps freshly-loaded sky shaders into the pipeline's embedded handles on a reload
+///
+/// When the watched `shaders/sky.vert`/`sky.frag` finish (re)loading, their source replaces the
+/// [`Shader`] stored at the fixed handles the [`MaterialPlugin`] pipeline references, so the sky
+/// re-renders with the edited GLSL without restarting. If the files are absent the embedded shaders
+/// set up in [`AtmospherePlugin::build`] stay in place.
+fn atmosphere_reload_shaders(
+    mut events: EventReader<AssetEvent<Shader>>,
+    watched: Res<WatchedSkyShaders>,
+    mut shaders: ResMut<Assets<Shader>>,
+) {
+    for event in events.iter() {
+        let (handle, target) = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                if *handle == watched.vertex {
+                    (handle, SKY_VERTEX_SHADER_HANDLE)
+                } else if *handle == watched.fragment {
+                    (handle, SKY_FRAGMENT_SHADER_HANDLE)
+                } else {
+                    continue;
+                }
+            }
+            AssetEvent::Removed { .. } => continue,
+        };
+
+        if let Some(shader) = shaders.get(handle).cloned() {
+            // Use `set` (not `set_untracked`) so the `Modified` event fires and the
+            // `MaterialPlugin` pipeline cache invalidates, live-updating the sky.
+            shaders.set(target, shader);
+        }
+    }
+}
+
 fn atmosphere_sky_follow(
     camera_transform_query: Query<&GlobalTransform, Without<Handle<AtmosphereMat>>>,
-    mut sky_transform_query: Query<&mut GlobalTransform, With<Handle<AtmosphereMat>>>,
+    mut sky_transform_query: Query<
+        (&mut GlobalTransform, Option<&SkyFollow>),
+        With<Handle<AtmosphereMat>>,
+    >,
     active_cameras: Res<ActiveCamera<Camera3d>>,
 ) {
-    if let Some(camera_3d) = active_cameras.get() {
-        if let Ok(camera_transform) = camera_transform_query.get(camera_3d) {
-            if let Some(mut sky_transform) = sky_transform_query.iter_mut().next() {
+    for (mut sky_transform, follow) in sky_transform_query.iter_mut() {
+        // Per-camera skies follow their linked camera; the resource-default sky follows the active one.
+        let camera = match follow {
+            Some(SkyFollow(camera)) => Some(*camera),
+            None => active_cameras.get(),
+        };
+
+        if let Some(camera) = camera {
+            if let Ok(camera_transform) = camera_transform_query.get(camera) {
                 sky_transform.translation = camera_transform.translation;
             }
         }
@@ -147,14 +302,34 @@ fn atmosphere_sky_follow(
 }
 
 fn atmosphere_dynamic_sky(
-    config: Res<AtmosphereMat>,
-    sky_mat_query: Query<&Handle<AtmosphereMat>>,
+    config: Option<Res<AtmosphereMat>>,
+    cameras: Query<&Atmosphere, Changed<Atmosphere>>,
+    sky_mat_query: Query<(&Handle<AtmosphereMat>, Option<&SkyFollow>)>,
     mut sky_materials: ResMut<Assets<AtmosphereMat>>,
 ) {
-    if config.is_changed() {
-        if let Some(sky_mat_handle) = sky_mat_query.iter().next() {
-            if let Some(sky_mat) = sky_materials.get_mut(sky_mat_handle) {
-                *sky_mat = config.deref().clone();
+    // Resource-default path: keep the single unlinked sky in sync with the global resource.
+    if let Some(config) = config {
+        if config.is_changed() {
+            for (sky_mat_handle, follow) in sky_mat_query.iter() {
+                if follow.is_none() {
+                    if let Some(sky_mat) = sky_materials.get_mut(sky_mat_handle) {
+                        *sky_mat = config.deref().clone();
+                    }
+                }
+            }
+        }
+    }
+
+    // Per-camera path: update each camera's own sky when its `Atmosphere` component changes.
+    if cameras.is_empty() {
+        return;
+    }
+    for (sky_mat_handle, follow) in sky_mat_query.iter() {
+        if let Some(SkyFollow(camera)) = follow {
+            if let Ok(atmosphere) = cameras.get(*camera) {
+                if let Some(sky_mat) = sky_materials.get_mut(sky_mat_handle) {
+                    *sky_mat = atmosphere.0.clone();
+                }
             }
         }
     }
@@ -0,0 +1,103 @@
+//! Drives the sun position from azimuth/elevation angles and an optional day/night cycle
+//!
+//! A single [`AtmosphereSun`] value animates the sky color, the shadow direction and the key-light
+//! intensity together, replacing the hand-written `atmosphere_dynamic_sky`-style system shown in the
+//! crate docs.
+
+use bevy::prelude::*;
+
+use crate::AtmosphereMat;
+
+/// Controls where the sun sits in the sky
+///
+/// Angles are stored in degrees for ergonomics and converted to radians internally. When
+/// `time_of_day` is `Some`, elevation advances over `day_length` seconds so a single value animates
+/// the whole sky.
+pub struct AtmosphereSun {
+    /// Compass direction of the sun, in degrees
+    pub azimuth: f32,
+    /// Height of the sun above the horizon, in degrees (negative is below the horizon)
+    pub elevation: f32,
+    /// Optional time-of-day phase in the `0.0..=1.0` range; `None` disables the cycle
+    pub time_of_day: Option<f32>,
+    /// Length of a full day in seconds, used when `time_of_day` is driving the elevation
+    pub day_length: f32,
+}
+
+impl Default for AtmosphereSun {
+    fn default() -> Self {
+        Self {
+            azimuth: 0.0,
+            elevation: 30.0,
+            time_of_day: None,
+            day_length: 120.0,
+        }
+    }
+}
+
+/// Marker for the [`DirectionalLight`] that should track the sun
+///
+/// Tag a light entity with this component to have its transform and illuminance follow
+/// [`AtmosphereSun`].
+#[derive(Component)]
+pub struct AtmosphereSunLight;
+
+/// Peak illuminance of the sun light when high in the sky, in lux
+const SUN_ILLUMINANCE: f32 = 100_000.0;
+
+/// Advances the sun and syncs it to the material and the tagged key light
+///
+/// The angles are converted to a direction vector
+/// `dir = (cos(elev)*sin(azim), sin(elev), cos(elev)*cos(azim))`, written into
+/// [`AtmosphereMat::sun_position`], and — when a [`AtmosphereSunLight`] exists — the light is aimed
+/// along `-dir` with its illuminance faded toward zero as the sun dips below the horizon.
+///
+/// Changes are only pushed when the angles actually move, to avoid thrashing the material every
+/// frame.
+pub(crate) fn atmosphere_sun(
+    time: Res<Time>,
+    mut sun: ResMut<AtmosphereSun>,
+    config: Option<ResMut<AtmosphereMat>>,
+    mut last_dir: Local<Option<Vec3>>,
+    mut light_query: Query<
+        (&mut Transform, &mut DirectionalLight),
+        With<AtmosphereSunLight>,
+    >,
+) {
+    // `AtmosphereMat` is user-inserted; without it there is no sky to steer.
+    let mut config = match config {
+        Some(config) => config,
+        None => return,
+    };
+
+    if let Some(phase) = sun.time_of_day.as_mut() {
+        *phase = (*phase + time.delta_seconds() / sun.day_length).fract();
+        // Sweep the full circle through a sine so the sun rises and sets smoothly, spending half the
+        // day above the horizon and half below rather than freezing at the poles.
+        sun.elevation = (*phase * std::f32::consts::TAU).sin() * 90.0;
+    }
+
+    let azimuth = sun.azimuth.to_radians();
+    let elevation = sun.elevation.to_radians();
+
+    let dir = Vec3::new(
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+        elevation.cos() * azimuth.cos(),
+    );
+
+    if last_dir.map_or(false, |previous| previous.abs_diff_eq(dir, f32::EPSILON)) {
+        return;
+    }
+    *last_dir = Some(dir);
+
+    config.sun_position = dir;
+
+    if let Some((mut transform, mut light)) = light_query.iter_mut().next() {
+        // Pick a non-parallel up so `looking_at` doesn't produce a NaN rotation at the zenith/nadir.
+        let up = if dir.y.abs() > 0.999 { Vec3::Z } else { Vec3::Y };
+        *transform = Transform::from_translation(dir).looking_at(Vec3::ZERO, up);
+        // Fade the key light out smoothly as the sun crosses the horizon.
+        light.illuminance = SUN_ILLUMINANCE * dir.y.clamp(0.0, 1.0);
+    }
+}